@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use tokio::sync::{oneshot, Mutex};
+
+#[derive(Default)]
+pub struct AbortRegistry {
+    senders: Mutex<HashMap<String, oneshot::Sender<()>>>,
+}
+
+impl AbortRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `request_id` as cancellable, returning the receiver the
+    /// caller should race against while retrying/streaming. A second
+    /// registration for the same id replaces the first, so only the latest
+    /// call for a reused id is cancellable.
+    pub async fn register(&self, request_id: &str) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+        self.senders.lock().await.insert(request_id.to_string(), tx);
+        rx
+    }
+
+    /// Cancels `request_id` if it's still registered, returning `false` if it
+    /// already finished or was never registered.
+    pub async fn cancel(&self, request_id: &str) -> bool {
+        match self.senders.lock().await.remove(request_id) {
+            Some(tx) => tx.send(()).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Removes `request_id` once its call has finished, so a late
+    /// `cancel_chat` for it is a no-op instead of reaching a future call
+    /// that happens to reuse the id.
+    pub async fn remove(&self, request_id: &str) {
+        self.senders.lock().await.remove(request_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cancel_returns_true_and_resolves_the_receiver() {
+        let registry = AbortRegistry::new();
+        let mut rx = registry.register("req-1").await;
+
+        assert!(registry.cancel("req-1").await);
+        assert!(rx.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn cancel_unknown_request_returns_false() {
+        let registry = AbortRegistry::new();
+        assert!(!registry.cancel("does-not-exist").await);
+    }
+
+    #[tokio::test]
+    async fn cancel_after_remove_returns_false() {
+        let registry = AbortRegistry::new();
+        registry.register("req-1").await;
+        registry.remove("req-1").await;
+
+        assert!(!registry.cancel("req-1").await);
+    }
+
+    #[tokio::test]
+    async fn registering_the_same_id_twice_only_the_latest_receiver_resolves() {
+        let registry = AbortRegistry::new();
+        let mut first_rx = registry.register("req-1").await;
+        let mut second_rx = registry.register("req-1").await;
+
+        assert!(registry.cancel("req-1").await);
+        assert!(second_rx.try_recv().is_ok());
+        assert!(first_rx.try_recv().is_err());
+    }
+}
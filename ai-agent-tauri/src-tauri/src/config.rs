@@ -0,0 +1,164 @@
+use serde::Deserialize;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClientConfig {
+    pub name: String,
+    pub provider: String,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub api_base: Option<String>,
+    #[serde(default)]
+    pub organization_id: Option<String>,
+    #[serde(default)]
+    pub proxy: Option<String>,
+    #[serde(default)]
+    pub connect_timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub default_model: Option<String>,
+}
+
+impl ClientConfig {
+    /// Builds the `reqwest::Client` shared by the rig agent and the MCP
+    /// transport, applying this client's proxy and connect-timeout settings.
+    pub fn build_http_client(&self) -> Result<reqwest::Client, String> {
+        let mut builder = reqwest::Client::builder();
+
+        if let Some(ms) = self.connect_timeout_ms {
+            builder = builder.connect_timeout(Duration::from_millis(ms));
+        }
+
+        if let Some(proxy_url) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy_url)
+                .map_err(|e| format!("invalid proxy `{}` for client `{}`: {}", proxy_url, self.name, e))?;
+            builder = builder.proxy(proxy);
+        }
+
+        builder
+            .build()
+            .map_err(|e| format!("failed to build http client for `{}`: {}", self.name, e))
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ClientsConfig {
+    #[serde(default)]
+    pub clients: Vec<ClientConfig>,
+}
+
+impl ClientsConfig {
+    /// Loads `clients.toml` (or the path in `CLIENTS_CONFIG`), falling back
+    /// to a single `openai` client built from `OPENAI_API_KEY` when no
+    /// config file is present so existing setups keep working unchanged.
+    pub fn load() -> Self {
+        let path = std::env::var("CLIENTS_CONFIG").unwrap_or_else(|_| "clients.toml".to_string());
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => match toml::from_str::<ClientsConfig>(&contents) {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!("Failed to parse {}: {} (falling back to env-based default)", path, e);
+                    Self::default_from_env()
+                }
+            },
+            Err(_) => Self::default_from_env(),
+        }
+    }
+
+    fn default_from_env() -> Self {
+        Self {
+            clients: vec![ClientConfig {
+                name: "default".to_string(),
+                provider: "openai".to_string(),
+                api_key: std::env::var("OPENAI_API_KEY").ok(),
+                api_base: None,
+                organization_id: None,
+                proxy: None,
+                connect_timeout_ms: None,
+                default_model: None,
+            }],
+        }
+    }
+
+    pub fn find(&self, name: &str) -> Option<&ClientConfig> {
+        self.clients.iter().find(|c| c.name == name)
+    }
+
+    pub fn default_client(&self) -> Option<&ClientConfig> {
+        self.find("default").or_else(|| self.clients.first())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_multiple_named_clients_from_toml() {
+        let config: ClientsConfig = toml::from_str(
+            r#"
+            [[clients]]
+            name = "openai-prod"
+            provider = "openai"
+            api_key = "sk-test"
+
+            [[clients]]
+            name = "local-proxy"
+            provider = "azure"
+            api_base = "https://example.test"
+            proxy = "socks5://127.0.0.1:1080"
+            connect_timeout_ms = 2000
+            default_model = "gpt-4o-mini"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.clients.len(), 2);
+        assert_eq!(config.find("openai-prod").unwrap().provider, "openai");
+        let proxied = config.find("local-proxy").unwrap();
+        assert_eq!(proxied.connect_timeout_ms, Some(2000));
+        assert_eq!(proxied.default_model.as_deref(), Some("gpt-4o-mini"));
+        assert!(config.find("missing").is_none());
+    }
+
+    #[test]
+    fn default_client_prefers_one_named_default() {
+        let config: ClientsConfig = toml::from_str(
+            r#"
+            [[clients]]
+            name = "other"
+            provider = "openai"
+
+            [[clients]]
+            name = "default"
+            provider = "anthropic"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.default_client().unwrap().provider, "anthropic");
+    }
+
+    #[test]
+    fn default_client_falls_back_to_first_when_none_named_default() {
+        let config: ClientsConfig = toml::from_str(
+            r#"
+            [[clients]]
+            name = "only-one"
+            provider = "openai"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.default_client().unwrap().name, "only-one");
+    }
+
+    #[test]
+    fn default_from_env_yields_single_openai_client() {
+        let config = ClientsConfig::default_from_env();
+        assert_eq!(config.clients.len(), 1);
+        assert_eq!(config.clients[0].name, "default");
+        assert_eq!(config.clients[0].provider, "openai");
+    }
+}
@@ -1,95 +1,330 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 use rig::{
     agent::MultiTurnStreamItem,
-    client::{completion::CompletionClientDyn, ProviderClient},
-    providers::{self, openai},
+    client::completion::CompletionClientDyn,
+    completion::Message,
+    providers,
     streaming::{StreamingPrompt, StreamedAssistantContent},
 };
 
-use std::{sync::Arc, time::Duration};
+use std::sync::Arc;
 use tauri::Emitter;
-use tokio::sync::Mutex;
 use serde::Serialize;
 use log::{error, info};
 use futures_util::StreamExt;
 
+mod abort;
+mod config;
+mod pool;
+mod retry;
+mod server;
+mod session;
 mod tool;
 
+/// Holds the outcome of the first chunk of a stream, decided up front by
+/// [`chat_with_agent`]'s retry loop, so the main streaming loop can replay
+/// it once instead of re-polling a fresh stream.
+enum FirstChunk<T> {
+    Item(T),
+    End,
+}
+
 #[derive(Clone, Serialize)]
 struct AgentChunk {
     delta: Option<String>,
     tool_calls: Option<serde_json::Value>,
 }
-static CONNECTION_POOL: tokio::sync::OnceCell<Arc<Mutex<Option<ConnectionHolder>>>> = tokio::sync::OnceCell::const_new();
+static CLIENTS_CONFIG: tokio::sync::OnceCell<config::ClientsConfig> = tokio::sync::OnceCell::const_new();
+static SESSIONS: tokio::sync::OnceCell<session::SessionManager> = tokio::sync::OnceCell::const_new();
+static ABORT_REGISTRY: tokio::sync::OnceCell<abort::AbortRegistry> = tokio::sync::OnceCell::const_new();
+static MCP_POOL: tokio::sync::OnceCell<Arc<pool::McpPool>> = tokio::sync::OnceCell::const_new();
+
+async fn sessions() -> &'static session::SessionManager {
+    SESSIONS.get_or_init(|| async { session::SessionManager::new() }).await
+}
 
-struct ConnectionHolder {
-    client: rmcp::Peer<rmcp::RoleClient>,
-    tools: Vec<rmcp::model::Tool>,
-    _service: Box<dyn std::any::Any + Send + Sync>,
+async fn abort_registry() -> &'static abort::AbortRegistry {
+    ABORT_REGISTRY.get_or_init(|| async { abort::AbortRegistry::new() }).await
 }
+
+/// Returns the shared MCP connection pool, spawning its background health
+/// check loop the first time it's accessed.
+pub(crate) async fn mcp_pool() -> Arc<pool::McpPool> {
+    MCP_POOL
+        .get_or_init(|| async {
+            let pool = Arc::new(pool::McpPool::new());
+
+            // Probe with the default client's proxy/timeout settings, same as
+            // an unqualified chat request would use, rather than a bare
+            // `reqwest::Client::new()` — so a configured proxy for reaching a
+            // private MCP server isn't silently bypassed if this loop races
+            // ahead of the first real lease and "wins" the slot's transport.
+            // A named, non-default client with different settings still wins
+            // the slot's transport if *it* leases first; this only changes
+            // what the background prober itself uses.
+            let clients_config = CLIENTS_CONFIG.get_or_init(|| async { config::ClientsConfig::load() }).await;
+            let health_check_client = clients_config
+                .default_client()
+                .and_then(|cfg| cfg.build_http_client().ok())
+                .unwrap_or_default();
+
+            tokio::spawn(pool.clone().run_health_checks(health_check_client));
+            pool
+        })
+        .await
+        .clone()
+}
+
+/// Cancels an in-flight `chat_with_agent` call by its `request_id`, if it's
+/// still running. Returns `false` if the request already finished or never
+/// existed.
 #[tauri::command]
-async fn chat_with_agent(message: String, app_handle: tauri::AppHandle) -> Result<(), String> {
-    eprintln!("=== CHAT_WITH_AGENT CALLED ===");
-    eprintln!("Received chat message: {}", message);
+async fn cancel_chat(request_id: String) -> bool {
+    abort_registry().await.cancel(&request_id).await
+}
 
-    if std::env::var("OPENAI_API_KEY").is_err() {
-        let error_msg = "OPENAI_API_KEY environment variable not set";
-        eprintln!("ERROR: {}", error_msg);
-        eprintln!("Current working directory: {:?}", std::env::current_dir());
+#[tauri::command]
+async fn create_session() -> String {
+    sessions().await.create().await
+}
 
-        let error_chunk = AgentChunk {
-            delta: Some(error_msg.to_string()),
-            tool_calls: None,
-        };
+#[tauri::command]
+async fn list_sessions() -> Vec<String> {
+    sessions().await.list().await
+}
 
-        app_handle.emit("agent-chunk", error_chunk).ok();
-        return Err(error_msg.to_string());
-    }
-    let openai_client = openai::Client::from_env();
+#[tauri::command]
+async fn delete_session(session_id: String) -> bool {
+    sessions().await.delete(&session_id).await
+}
 
-    let mut agent = openai_client
-            .agent(providers::openai::GPT_4O)
-            .preamble("You are a helpful assistant. Use your tools when necessary.")
-            .max_tokens(1024)
-            .tool(tool::GetCurrentTime);
+/// Builds the dyn completion client for `client_name` (or the configured
+/// default) from `clients.toml`, plus the `reqwest::Client` derived from its
+/// proxy/timeout settings and the client's configured `default_model` (used
+/// when the caller doesn't pin one). The same http client is reused for the
+/// MCP transport so both hops go through one consistently-configured client.
+pub(crate) async fn build_client(client_name: Option<&str>) -> Result<(Box<dyn CompletionClientDyn>, reqwest::Client, Option<String>), String> {
+    let clients_config = CLIENTS_CONFIG.get_or_init(|| async { config::ClientsConfig::load() }).await;
+
+    let client_cfg = match client_name {
+        Some(name) => clients_config
+            .find(name)
+            .ok_or_else(|| format!("no client named `{}` in clients.toml", name))?,
+        None => clients_config
+            .default_client()
+            .ok_or_else(|| "no clients configured in clients.toml".to_string())?,
+    };
 
-    match get_connection().await {
-        Ok((tools, client)) => {
-            info!("âœ“ Connected to MCP server with {} tools", tools.len());
-            for tool in &tools {
-                info!("  - Tool: {}", tool.name);
+    let api_key = client_cfg.api_key.clone().unwrap_or_default();
+    let http_client = client_cfg.build_http_client()?;
+
+    let completion_client: Box<dyn CompletionClientDyn> = match client_cfg.provider.as_str() {
+        "openai" => {
+            let mut builder = providers::openai::Client::builder(&api_key).custom_client(http_client.clone());
+            if let Some(base) = &client_cfg.api_base {
+                builder = builder.base_url(base);
             }
-            for tool in tools {
-                agent = agent.rmcp_tool(tool, client.clone());
+            if let Some(org) = &client_cfg.organization_id {
+                builder = builder.organization_id(org);
             }
+            Box::new(
+                builder
+                    .build()
+                    .map_err(|e| format!("failed to build openai client `{}`: {}", client_cfg.name, e))?,
+            )
+        }
+        "azure" => {
+            let api_base = client_cfg
+                .api_base
+                .clone()
+                .ok_or_else(|| format!("client `{}`: azure provider requires api_base", client_cfg.name))?;
+            Box::new(
+                providers::azure::Client::builder(&api_key)
+                    .api_base(&api_base)
+                    .custom_client(http_client.clone())
+                    .build()
+                    .map_err(|e| format!("failed to build azure client `{}`: {}", client_cfg.name, e))?,
+            )
         }
+        "anthropic" => {
+            let mut builder = providers::anthropic::Client::builder(&api_key).custom_client(http_client.clone());
+            if let Some(base) = &client_cfg.api_base {
+                builder = builder.base_url(base);
+            }
+            Box::new(
+                builder
+                    .build()
+                    .map_err(|e| format!("failed to build anthropic client `{}`: {}", client_cfg.name, e))?,
+            )
+        }
+        other => return Err(format!("client `{}`: unknown provider `{}`", client_cfg.name, other)),
+    };
+
+    Ok((completion_client, http_client, client_cfg.default_model.clone()))
+}
+
+#[tauri::command]
+async fn chat_with_agent(
+    message: String,
+    client_name: Option<String>,
+    model: Option<String>,
+    session_id: Option<String>,
+    request_id: String,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    eprintln!("=== CHAT_WITH_AGENT CALLED ===");
+    eprintln!("Received chat message: {}", message);
+
+    let (completion_client, http_client, default_model) = match build_client(client_name.as_deref()).await {
+        Ok(client) => client,
         Err(e) => {
-            error!("Failed to connect to MCP server: {}", e);
-            error!("Agent will run without web search capability");
+            eprintln!("ERROR: {}", e);
+            let error_chunk = AgentChunk {
+                delta: Some(e.clone()),
+                tool_calls: None,
+            };
+            app_handle.emit("agent-chunk", error_chunk).ok();
+            return Err(e);
         }
+    };
+
+    let model = model
+        .or(default_model)
+        .unwrap_or_else(|| providers::openai::GPT_4O.to_string());
+
+    let mut agent = completion_client
+            .agent(&model)
+            .preamble("You are a helpful assistant. Use your tools when necessary.")
+            .max_tokens(1024)
+            .tool(tool::GetCurrentTime);
+
+    let mcp_tools = mcp_pool().await.aggregate_tools(http_client).await;
+    info!("✓ Aggregated {} MCP tools across configured servers", mcp_tools.len());
+    for (client, tool_def) in mcp_tools {
+        info!("  - Tool: {}", tool_def.name);
+        agent = agent.rmcp_tool(tool_def, client);
     }
 
     let agent = agent.build();
 
-  
+    let history = match &session_id {
+        Some(id) => sessions().await.history(id).await,
+        None => Vec::new(),
+    };
+
+    let mut assistant_text = String::new();
+
+    // Registered before the retry loop (not after) so `cancel_chat` can
+    // interrupt a call that's still retrying its initial `stream_chat`,
+    // not just one that's already streaming.
+    let mut cancel_rx = abort_registry().await.register(&request_id).await;
 
-    let mut stream = agent.stream_prompt(&message).await;
+    // Retry the initial stream_chat call on transient failures (timeouts,
+    // resets, 429/5xx) before giving up, surfacing an `agent-retry` status
+    // between attempts instead of failing on the first blip.
+    let mut attempt: u32 = 0;
+    let (mut stream, mut first_chunk) = loop {
+        let mut candidate = agent.stream_chat(&message, history.clone()).await;
+        match candidate.next().await {
+            Some(Err(e)) if attempt + 1 < retry::MAX_ATTEMPTS && retry::is_transient(&e) => {
+                attempt += 1;
+                let delay = retry::backoff_for_attempt(attempt);
+                eprintln!("Stream error on attempt {} ({}), retrying in {:?}", attempt, e, delay);
+                app_handle
+                    .emit("agent-retry", serde_json::json!({ "attempt": attempt, "delay_ms": delay.as_millis() }))
+                    .ok();
+
+                tokio::select! {
+                    biased;
+                    _ = &mut cancel_rx => {
+                        eprintln!("Chat cancelled while retrying: {}", request_id);
+                        abort_registry().await.remove(&request_id).await;
+                        app_handle.emit("agent-cancelled", &request_id).ok();
+                        return Ok(());
+                    }
+                    _ = tokio::time::sleep(delay) => {}
+                }
+            }
+            Some(item) => break (candidate, FirstChunk::Item(item)),
+            None => break (candidate, FirstChunk::End),
+        }
+    };
+
+    loop {
+        let chunk = match std::mem::replace(&mut first_chunk, FirstChunk::End) {
+            FirstChunk::Item(item) => item,
+            FirstChunk::End => {
+                tokio::select! {
+                    biased;
+                    _ = &mut cancel_rx => {
+                        eprintln!("Chat cancelled: {}", request_id);
+                        abort_registry().await.remove(&request_id).await;
+                        app_handle.emit("agent-cancelled", &request_id).ok();
+                        return Ok(());
+                    }
+                    chunk = stream.next() => match chunk {
+                        Some(chunk) => chunk,
+                        None => break,
+                    },
+                }
+            }
+        };
 
-    while let Some(chunk) = stream.next().await {
         match chunk {
             Ok(MultiTurnStreamItem::StreamItem(content)) => {
-    
-                if let StreamedAssistantContent::Text(text) = content {
-                    let text_str = text.to_string();
-                    eprintln!("Streaming: {}", text_str);
-
-                    let agent_chunk = AgentChunk {
-                        delta: Some(text_str),
-                        tool_calls: None,
-                    };
-
-                    if let Err(e) = app_handle.emit("agent-chunk", agent_chunk) {
-                        eprintln!("Failed to emit chunk: {:?}", e);
+                // ToolCall/ToolResult are matched as StreamedAssistantContent
+                // variants (the same enum Text already comes from) rather than
+                // as a sibling of StreamItem/FinalResponse on MultiTurnStreamItem
+                // - that's the only shape consistent with how Text is already
+                // matched here. Not compiler-verified: this tree has no
+                // Cargo.toml/Cargo.lock anywhere (not even the baseline commit),
+                // and this sandbox has neither network access nor a vendored
+                // registry to add one with, so `cargo check` against the pinned
+                // rig-core version genuinely cannot be run here. Run it before
+                // merging.
+                match content {
+                    StreamedAssistantContent::Text(text) => {
+                        let text_str = text.to_string();
+                        eprintln!("Streaming: {}", text_str);
+                        assistant_text.push_str(&text_str);
+
+                        let agent_chunk = AgentChunk {
+                            delta: Some(text_str),
+                            tool_calls: None,
+                        };
+
+                        if let Err(e) = app_handle.emit("agent-chunk", agent_chunk) {
+                            eprintln!("Failed to emit chunk: {:?}", e);
+                        }
+                    }
+                    StreamedAssistantContent::ToolCall(tool_call) => {
+                        eprintln!("Tool call: {} {:?}", tool_call.function.name, tool_call.function.arguments);
+
+                        let event = serde_json::json!({
+                            "id": tool_call.id,
+                            "name": tool_call.function.name,
+                            "arguments": tool_call.function.arguments,
+                        });
+
+                        if let Err(e) = app_handle.emit("agent-tool", &event) {
+                            eprintln!("Failed to emit tool call: {:?}", e);
+                        }
+                    }
+                    StreamedAssistantContent::ToolResult(tool_result) => {
+                        eprintln!("Tool result: {:?}", tool_result);
+
+                        let event = serde_json::json!({
+                            "id": tool_result.id,
+                            "output": tool_result.content,
+                        });
+
+                        if let Err(e) = app_handle.emit("agent-tool", &event) {
+                            eprintln!("Failed to emit tool result: {:?}", e);
+                        }
+                    }
+                    _other => {
+                        // Reasoning deltas and other content variants aren't
+                        // surfaced to the frontend yet.
                     }
                 }
             }
@@ -108,97 +343,44 @@ async fn chat_with_agent(message: String, app_handle: tauri::AppHandle) -> Resul
                     tool_calls: None,
                 };
                 app_handle.emit("agent-chunk", error_chunk).ok();
+                abort_registry().await.remove(&request_id).await;
                 return Err(format!("Stream error: {}", e));
             }
         }
     }
 
-    eprintln!("Stream completed successfully");
-    Ok(())
-}
-
-async fn get_connection() -> Result<(Vec<rmcp::model::Tool>, rmcp::Peer<rmcp::RoleClient>), Box<dyn std::error::Error + Send + Sync>> {
-    let pool = CONNECTION_POOL
-        .get_or_init(|| async { Arc::new(Mutex::new(None)) })
-        .await;
-    let mut guard = pool.lock().await;
+    abort_registry().await.remove(&request_id).await;
 
-    if let Some(holder) = guard.as_ref() {
-        if tokio::time::timeout(Duration::from_secs(2), holder.client.list_tools(Default::default())).await.is_ok() {
-            return Ok((holder.tools.clone(), holder.client.clone()));
-        }
-        *guard = None;
+    if let Some(id) = &session_id {
+        sessions()
+            .await
+            .append(id, Message::user(&message), Message::assistant(&assistant_text))
+            .await;
     }
 
-    let holder = create_connection().await?;
-    let tools = holder.tools.clone();
-    let client = holder.client.clone();
-    *guard = Some(holder);
-    Ok((tools, client))
-}
-async fn create_connection() -> Result<ConnectionHolder, Box<dyn std::error::Error + Send + Sync>> {
-    use rmcp::{model::{ClientCapabilities, ClientInfo, Implementation}, ServiceExt};
-    use rmcp::transport::streamable_http_client::StreamableHttpClientTransportConfig;
-
-    let server_url = std::env::var("MCP_SERVER_URL").unwrap_or_else(|_| "http://localhost:8081".to_string());
-    let endpoint = format!("{}/mcp", server_url);
-
-    let uri: std::sync::Arc<str> = endpoint.into();
-    let config = StreamableHttpClientTransportConfig {
-        uri,
-        ..Default::default()
-    };
-
-    let transport = rmcp::transport::StreamableHttpClientTransport::with_client(
-        reqwest::Client::new(),
-        config
-    );
-
-    let client_info = ClientInfo {
-        protocol_version: Default::default(),
-        capabilities: ClientCapabilities::default(),
-        client_info: Implementation {
-            name: "agent-conversation".to_string(),
-            version: "0.1.0".to_string(),
-            title: None,
-            website_url: None,
-            icons: None,
-        },
-    };
-
-    let service = client_info.serve(transport).await?;
-    let client = service.peer().clone();
-    let mut tools = tokio::time::timeout(Duration::from_secs(10), client.list_tools(Default::default())).await??.tools;
-
-    for tool in &mut tools {
-        let mut schema = (*tool.input_schema).clone();
-        if let Some(props) = schema.get("properties") {
-            if let Some(props_obj) = props.as_object() {
-                let required: Vec<String> = props_obj.keys().cloned().collect();
-                schema.insert("required".to_string(), serde_json::json!(required));
-            }
-        }
-        tool.input_schema = std::sync::Arc::new(schema);
-    }
-
-    Ok(ConnectionHolder {
-        client,
-        tools,
-        _service: Box::new(service),
-    })
+    eprintln!("Stream completed successfully");
+    Ok(())
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     dotenv::dotenv().ok();
 
-    if std::env::var("OPENAI_API_KEY").is_err() {
-        eprintln!("OPENAI_API_KEY environment variable not set");
+    if config::ClientsConfig::load().clients.is_empty() {
+        eprintln!("No clients configured: add a clients.toml or set OPENAI_API_KEY");
     }
 
+    tauri::async_runtime::spawn(server::maybe_serve());
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![chat_with_agent])
+        .invoke_handler(tauri::generate_handler![
+            chat_with_agent,
+            cancel_chat,
+            create_session,
+            list_sessions,
+            delete_session
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
\ No newline at end of file
@@ -0,0 +1,285 @@
+use crate::retry;
+use futures_util::future::join_all;
+use log::{error, info};
+use rmcp::{
+    model::{ClientCapabilities, ClientInfo, Implementation},
+    transport::{streamable_http_client::StreamableHttpClientTransportConfig, StreamableHttpClientTransport},
+    Peer, RoleClient, ServiceExt,
+};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+const MIN_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+struct ConnectionHolder {
+    client: Peer<RoleClient>,
+    tools: Vec<rmcp::model::Tool>,
+    _service: Box<dyn std::any::Any + Send + Sync>,
+}
+
+enum Entry {
+    Connected(ConnectionHolder),
+    Backoff { next_attempt: Instant, delay: Duration },
+}
+
+pub struct McpPool {
+    servers: Vec<String>,
+    // Each server gets its own slot behind its own mutex, so leasing a slow
+    // or dead server only ever blocks concurrent leases of *that* server
+    // (acting as an in-flight-connect guard) and never delays leases to any
+    // other server. `slots` itself is locked only to get-or-create a slot.
+    slots: Mutex<HashMap<String, Arc<Mutex<Option<Entry>>>>>,
+}
+
+impl McpPool {
+    /// Reads the configured MCP server URLs from `MCP_SERVER_URLS` (comma
+    /// separated) or the legacy single-server `MCP_SERVER_URL`, falling
+    /// back to the same localhost default as before.
+    pub fn new() -> Self {
+        let raw = std::env::var("MCP_SERVER_URLS")
+            .or_else(|_| std::env::var("MCP_SERVER_URL"))
+            .unwrap_or_else(|_| "http://localhost:8081".to_string());
+
+        let servers = raw
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        Self {
+            servers,
+            slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn slot_for(&self, server_url: &str) -> Arc<Mutex<Option<Entry>>> {
+        self.slots
+            .lock()
+            .await
+            .entry(server_url.to_string())
+            .or_insert_with(|| Arc::new(Mutex::new(None)))
+            .clone()
+    }
+
+    /// Leases a healthy connection to `server_url`, lazily (re)establishing
+    /// it as needed. Returns `None` if the server is unreachable or is
+    /// currently in its backoff window. Only this server's own slot is
+    /// locked for the duration of the connect/retry sequence, so leasing a
+    /// slow or dead server never blocks a concurrent lease of another.
+    async fn lease(&self, server_url: &str, http_client: reqwest::Client) -> Option<(Vec<rmcp::model::Tool>, Peer<RoleClient>)> {
+        let slot = self.slot_for(server_url).await;
+        let mut entry = slot.lock().await;
+
+        if let Some(Entry::Backoff { next_attempt, .. }) = entry.as_ref() {
+            if Instant::now() < *next_attempt {
+                return None;
+            }
+        }
+
+        if let Some(Entry::Connected(holder)) = entry.as_ref() {
+            if tokio::time::timeout(Duration::from_secs(2), holder.client.list_tools(Default::default()))
+                .await
+                .is_ok()
+            {
+                return Some((holder.tools.clone(), holder.client.clone()));
+            }
+        }
+
+        // Transient failures (timeouts, resets, 5xx) get a few retries, spaced
+        // by `retry::backoff_for_attempt`, before the server is parked in its
+        // backoff window; a
+        // non-transient failure (e.g. a bad URL) skips straight to backoff.
+        let mut last_err = None;
+        let mut connected = None;
+        for attempt in 1..=retry::MAX_ATTEMPTS {
+            match connect(server_url, http_client.clone()).await {
+                Ok(holder) => {
+                    connected = Some(holder);
+                    break;
+                }
+                Err(e) => {
+                    let transient = retry::is_transient(&e);
+                    last_err = Some(e);
+                    if !transient || attempt == retry::MAX_ATTEMPTS {
+                        break;
+                    }
+                    let delay = retry::backoff_for_attempt(attempt);
+                    error!("MCP server {} connection attempt {} failed ({}), retrying in {:?}", server_url, attempt, last_err.as_ref().unwrap(), delay);
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+
+        match connected {
+            Some(holder) => {
+                info!("Connected to MCP server {} ({} tools)", server_url, holder.tools.len());
+                let tools = holder.tools.clone();
+                let client = holder.client.clone();
+                *entry = Some(Entry::Connected(holder));
+                Some((tools, client))
+            }
+            None => {
+                let delay = match entry.as_ref() {
+                    Some(Entry::Backoff { delay, .. }) => (*delay * 2).min(MAX_BACKOFF),
+                    _ => MIN_BACKOFF,
+                };
+                error!("MCP server {} unreachable ({}), retrying in {:?}", server_url, last_err.unwrap(), delay);
+                *entry = Some(Entry::Backoff { next_attempt: Instant::now() + delay, delay });
+                None
+            }
+        }
+    }
+
+    /// Aggregates tools from every reachable configured server. Servers are
+    /// leased concurrently, so one down or mid-backoff server doesn't
+    /// serialize the connect/retry sequence of the others.
+    pub async fn aggregate_tools(&self, http_client: reqwest::Client) -> Vec<(Peer<RoleClient>, rmcp::model::Tool)> {
+        let leases = join_all(
+            self.servers
+                .iter()
+                .map(|server_url| self.lease(server_url, http_client.clone())),
+        )
+        .await;
+
+        leases
+            .into_iter()
+            .flatten()
+            .flat_map(|(tools, client)| tools.into_iter().map(move |tool| (client.clone(), tool)))
+            .collect()
+    }
+
+    /// Background loop that periodically re-probes every configured server
+    /// so a dead one is evicted (and its backoff started) even if no chat
+    /// happens to lease it in the meantime.
+    pub async fn run_health_checks(self: Arc<Self>, http_client: reqwest::Client) {
+        let mut interval = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            for server_url in self.servers.clone() {
+                self.lease(&server_url, http_client.clone()).await;
+            }
+        }
+    }
+}
+
+async fn connect(server_url: &str, http_client: reqwest::Client) -> Result<ConnectionHolder, Box<dyn std::error::Error + Send + Sync>> {
+    let endpoint = format!("{}/mcp", server_url);
+    let uri: std::sync::Arc<str> = endpoint.into();
+    let config = StreamableHttpClientTransportConfig {
+        uri,
+        ..Default::default()
+    };
+
+    let transport = StreamableHttpClientTransport::with_client(http_client, config);
+
+    let client_info = ClientInfo {
+        protocol_version: Default::default(),
+        capabilities: ClientCapabilities::default(),
+        client_info: Implementation {
+            name: "agent-conversation".to_string(),
+            version: "0.1.0".to_string(),
+            title: None,
+            website_url: None,
+            icons: None,
+        },
+    };
+
+    let service = client_info.serve(transport).await?;
+    let client = service.peer().clone();
+    let mut tools = tokio::time::timeout(Duration::from_secs(10), client.list_tools(Default::default())).await??.tools;
+
+    for tool in &mut tools {
+        let mut schema = (*tool.input_schema).clone();
+        if let Some(props) = schema.get("properties") {
+            if let Some(props_obj) = props.as_object() {
+                let required: Vec<String> = props_obj.keys().cloned().collect();
+                schema.insert("required".to_string(), serde_json::json!(required));
+            }
+        }
+        tool.input_schema = std::sync::Arc::new(schema);
+    }
+
+    Ok(ConnectionHolder {
+        client,
+        tools,
+        _service: Box::new(service),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An address nothing listens on, so `connect()` fails fast with a
+    // connection-refused error instead of waiting out a real timeout.
+    const UNREACHABLE_SERVER: &str = "http://127.0.0.1:9";
+
+    fn pool_with_server(server_url: &str) -> McpPool {
+        McpPool {
+            servers: vec![server_url.to_string()],
+            slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn entry_delay(pool: &McpPool, server_url: &str) -> Option<Duration> {
+        let slot = pool.slots.lock().await.get(server_url)?.clone();
+        match &*slot.lock().await {
+            Some(Entry::Backoff { delay, .. }) => Some(*delay),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn lease_enters_backoff_after_exhausting_retries_on_an_unreachable_server() {
+        let pool = pool_with_server(UNREACHABLE_SERVER);
+
+        assert!(pool.lease(UNREACHABLE_SERVER, reqwest::Client::new()).await.is_none());
+        assert_eq!(entry_delay(&pool, UNREACHABLE_SERVER).await, Some(MIN_BACKOFF));
+    }
+
+    #[tokio::test]
+    async fn lease_returns_none_immediately_while_in_the_backoff_window() {
+        let pool = pool_with_server(UNREACHABLE_SERVER);
+        pool.lease(UNREACHABLE_SERVER, reqwest::Client::new()).await;
+
+        let started = Instant::now();
+        assert!(pool.lease(UNREACHABLE_SERVER, reqwest::Client::new()).await.is_none());
+        // No connect attempt should have been made: the window hasn't elapsed.
+        assert!(started.elapsed() < Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn lease_doubles_the_backoff_delay_on_a_second_consecutive_failure() {
+        let pool = pool_with_server(UNREACHABLE_SERVER);
+        pool.lease(UNREACHABLE_SERVER, reqwest::Client::new()).await;
+
+        {
+            let slot = pool.slots.lock().await.get(UNREACHABLE_SERVER).unwrap().clone();
+            if let Some(Entry::Backoff { next_attempt, .. }) = slot.lock().await.as_mut() {
+                *next_attempt = Instant::now();
+            }
+        }
+
+        pool.lease(UNREACHABLE_SERVER, reqwest::Client::new()).await;
+        assert_eq!(entry_delay(&pool, UNREACHABLE_SERVER).await, Some(MIN_BACKOFF * 2));
+    }
+
+    #[tokio::test]
+    async fn lease_caps_the_backoff_delay_at_max_backoff() {
+        let pool = pool_with_server(UNREACHABLE_SERVER);
+        {
+            let slot = pool.slot_for(UNREACHABLE_SERVER).await;
+            *slot.lock().await = Some(Entry::Backoff { next_attempt: Instant::now(), delay: MAX_BACKOFF });
+        }
+
+        pool.lease(UNREACHABLE_SERVER, reqwest::Client::new()).await;
+        assert_eq!(entry_delay(&pool, UNREACHABLE_SERVER).await, Some(MAX_BACKOFF));
+    }
+}
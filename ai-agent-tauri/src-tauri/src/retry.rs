@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+pub const MAX_ATTEMPTS: u32 = 3;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(8);
+
+/// Known transient HTTP status codes paired with their standard reason
+/// phrase, so a message is only classed as that status if both appear
+/// together rather than on a bare digit substring (a token limit like
+/// "max_tokens 500" or a port number shouldn't be misread as a 500).
+const TRANSIENT_STATUS_REASONS: [(&str, &str); 5] = [
+    ("429", "too many requests"),
+    ("500", "internal server error"),
+    ("502", "bad gateway"),
+    ("503", "service unavailable"),
+    ("504", "gateway timeout"),
+];
+
+/// Exponential backoff starting at 500ms and doubling per attempt, capped
+/// at `MAX_BACKOFF`.
+pub fn backoff_for_attempt(attempt: u32) -> Duration {
+    let millis = INITIAL_BACKOFF.as_millis() as u64 * 2u64.saturating_pow(attempt.saturating_sub(1));
+    Duration::from_millis(millis).min(MAX_BACKOFF)
+}
+
+/// Treats timeouts, refused or reset connections, and 429/5xx responses as
+/// worth retrying; anything else (auth errors, bad requests) fails fast.
+pub fn is_transient<E: std::fmt::Display>(error: &E) -> bool {
+    let message = error.to_string().to_lowercase();
+
+    let text_markers = ["timeout", "timed out", "connection reset", "connection refused"];
+    if text_markers.iter().any(|needle| message.contains(needle)) {
+        return true;
+    }
+
+    TRANSIENT_STATUS_REASONS
+        .iter()
+        .any(|(code, reason)| message.contains(code) && message.contains(reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_from_500ms() {
+        assert_eq!(backoff_for_attempt(1), Duration::from_millis(500));
+        assert_eq!(backoff_for_attempt(2), Duration::from_millis(1000));
+        assert_eq!(backoff_for_attempt(3), Duration::from_millis(2000));
+    }
+
+    #[test]
+    fn backoff_caps_at_max() {
+        assert_eq!(backoff_for_attempt(10), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn is_transient_matches_timeouts_and_rate_limits() {
+        assert!(is_transient(&"request timed out"));
+        assert!(is_transient(&"429 Too Many Requests"));
+        assert!(is_transient(&"502 Bad Gateway"));
+        assert!(is_transient(&"503 Service Unavailable"));
+        assert!(is_transient(&"504 Gateway Timeout"));
+    }
+
+    #[test]
+    fn is_transient_rejects_non_transient_errors() {
+        assert!(!is_transient(&"invalid api key"));
+        assert!(!is_transient(&"400 Bad Request"));
+    }
+
+    #[test]
+    fn is_transient_rejects_digits_that_are_not_a_status_code() {
+        // A token-limit or port number containing "500"/"502" shouldn't be
+        // misread as a 500/502 response and needlessly retried.
+        assert!(!is_transient(&"request exceeded max_tokens 500"));
+        assert!(!is_transient(&"failed to bind to port 5029"));
+    }
+}
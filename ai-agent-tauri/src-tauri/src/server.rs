@@ -0,0 +1,244 @@
+use axum::{
+    extract::State,
+    response::{
+        sse::{Event, Sse},
+        Html, IntoResponse,
+    },
+    routing::{get, post},
+    Json, Router,
+};
+use futures_util::{Stream, StreamExt};
+use rig::{
+    agent::MultiTurnStreamItem,
+    client::completion::CompletionClientDyn,
+    completion::Message,
+    streaming::{StreamedAssistantContent, StreamingPrompt},
+};
+use serde::{Deserialize, Serialize};
+use std::{convert::Infallible, sync::Arc};
+
+use crate::{build_client, mcp_pool, tool};
+
+const PLAYGROUND_HTML: &str = include_str!("server_playground.html");
+
+#[derive(Clone)]
+struct ServerState {
+    client_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionRequest {
+    model: String,
+    messages: Vec<ChatMessage>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct ChatMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct ChatCompletionChunk {
+    id: String,
+    object: &'static str,
+    model: String,
+    choices: Vec<ChunkChoice>,
+}
+
+#[derive(Serialize)]
+struct ChunkChoice {
+    index: u32,
+    delta: ChunkDelta,
+    finish_reason: Option<&'static str>,
+}
+
+#[derive(Serialize, Default)]
+struct ChunkDelta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+}
+
+/// Starts the server in the background if `AGENT_SERVER_ADDR` is set (e.g.
+/// `127.0.0.1:8090`). A no-op otherwise, so the Tauri app behaves exactly as
+/// before when the variable is absent.
+pub async fn maybe_serve() {
+    let Ok(addr) = std::env::var("AGENT_SERVER_ADDR") else {
+        return;
+    };
+
+    let listener = match tokio::net::TcpListener::bind(&addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to bind AGENT_SERVER_ADDR {}: {}", addr, e);
+            return;
+        }
+    };
+
+    let state = Arc::new(ServerState {
+        client_name: std::env::var("AGENT_SERVER_CLIENT").ok(),
+    });
+
+    let app = Router::new()
+        .route("/", get(playground))
+        .route("/v1/chat/completions", post(chat_completions))
+        .with_state(state);
+
+    eprintln!("OpenAI-compatible agent server listening on http://{}", addr);
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            eprintln!("Agent HTTP server stopped: {}", e);
+        }
+    });
+}
+
+async fn playground() -> Html<&'static str> {
+    Html(PLAYGROUND_HTML)
+}
+
+/// Maps OpenAI-style chat messages to `rig` messages, treating anything
+/// other than `"assistant"` (system prompts included) as a user turn since
+/// the agent's own preamble already carries the system prompt.
+fn into_history(messages: Vec<ChatMessage>) -> Vec<Message> {
+    messages
+        .into_iter()
+        .map(|m| match m.role.as_str() {
+            "assistant" => Message::assistant(&m.content),
+            _ => Message::user(&m.content),
+        })
+        .collect()
+}
+
+async fn chat_completions(
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<ChatCompletionRequest>,
+) -> impl IntoResponse {
+    let mut messages = request.messages;
+    let Some(last) = messages.pop() else {
+        return Sse::new(futures_util::stream::empty()).into_response();
+    };
+
+    let stream = stream_completion(state, request.model, last.content, into_history(messages)).await;
+    Sse::new(stream).into_response()
+}
+
+async fn stream_completion(
+    state: Arc<ServerState>,
+    model: String,
+    prompt: String,
+    history: Vec<Message>,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    let completion_id = format!("chatcmpl-{}", uuid::Uuid::new_v4());
+
+    async_stream::stream! {
+        let (completion_client, http_client, _default_model) = match build_client(state.client_name.as_deref()).await {
+            Ok(client) => client,
+            Err(e) => {
+                yield Ok(Event::default().data(format!("error: {}", e)));
+                return;
+            }
+        };
+
+        let mut agent = completion_client
+            .agent(&model)
+            .preamble("You are a helpful assistant. Use your tools when necessary.")
+            .max_tokens(1024)
+            .tool(tool::GetCurrentTime);
+
+        for (client, tool_def) in mcp_pool().await.aggregate_tools(http_client).await {
+            agent = agent.rmcp_tool(tool_def, client);
+        }
+
+        let agent = agent.build();
+        let mut response_stream = agent.stream_chat(&prompt, history).await;
+
+        while let Some(chunk) = response_stream.next().await {
+            match chunk {
+                Ok(MultiTurnStreamItem::StreamItem(StreamedAssistantContent::Text(text))) => {
+                    let payload = ChatCompletionChunk {
+                        id: completion_id.clone(),
+                        object: "chat.completion.chunk",
+                        model: model.clone(),
+                        choices: vec![ChunkChoice {
+                            index: 0,
+                            delta: ChunkDelta { content: Some(text.to_string()) },
+                            finish_reason: None,
+                        }],
+                    };
+                    if let Ok(data) = serde_json::to_string(&payload) {
+                        yield Ok(Event::default().data(data));
+                    }
+                }
+                Ok(MultiTurnStreamItem::FinalResponse(_)) => break,
+                Ok(_other) => continue,
+                Err(e) => {
+                    yield Ok(Event::default().data(format!("error: {}", e)));
+                    return;
+                }
+            }
+        }
+
+        let final_payload = ChatCompletionChunk {
+            id: completion_id.clone(),
+            object: "chat.completion.chunk",
+            model: model.clone(),
+            choices: vec![ChunkChoice {
+                index: 0,
+                delta: ChunkDelta::default(),
+                finish_reason: Some("stop"),
+            }],
+        };
+        if let Ok(data) = serde_json::to_string(&final_payload) {
+            yield Ok(Event::default().data(data));
+        }
+        yield Ok(Event::default().data("[DONE]"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chat_message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage { role: role.to_string(), content: content.to_string() }
+    }
+
+    #[test]
+    fn into_history_maps_assistant_role_to_assistant_message() {
+        let history = into_history(vec![chat_message("assistant", "hi")]);
+        assert_eq!(history.len(), 1);
+        assert!(matches!(history[0], Message::Assistant { .. }));
+    }
+
+    #[test]
+    fn into_history_treats_unknown_roles_as_user() {
+        let history = into_history(vec![chat_message("system", "be nice"), chat_message("user", "hello")]);
+        assert_eq!(history.len(), 2);
+        assert!(matches!(history[0], Message::User { .. }));
+        assert!(matches!(history[1], Message::User { .. }));
+    }
+
+    #[test]
+    fn into_history_preserves_message_order_and_count() {
+        let history = into_history(vec![
+            chat_message("user", "one"),
+            chat_message("assistant", "two"),
+            chat_message("user", "three"),
+        ]);
+        assert_eq!(history.len(), 3);
+        assert!(matches!(history[0], Message::User { .. }));
+        assert!(matches!(history[1], Message::Assistant { .. }));
+        assert!(matches!(history[2], Message::User { .. }));
+    }
+
+    #[test]
+    fn into_history_is_empty_for_no_messages() {
+        assert!(into_history(vec![]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn playground_serves_the_bundled_html() {
+        let Html(body) = playground().await;
+        assert_eq!(body, PLAYGROUND_HTML);
+    }
+}
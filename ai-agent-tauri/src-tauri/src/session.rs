@@ -0,0 +1,103 @@
+use rig::completion::Message;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+pub type SessionId = String;
+
+#[derive(Default)]
+pub struct SessionManager {
+    sessions: Mutex<HashMap<SessionId, Vec<Message>>>,
+}
+
+impl SessionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn create(&self) -> SessionId {
+        let session_id = Uuid::new_v4().to_string();
+        self.sessions.lock().await.insert(session_id.clone(), Vec::new());
+        session_id
+    }
+
+    pub async fn list(&self) -> Vec<SessionId> {
+        self.sessions.lock().await.keys().cloned().collect()
+    }
+
+    pub async fn delete(&self, session_id: &str) -> bool {
+        self.sessions.lock().await.remove(session_id).is_some()
+    }
+
+    /// Returns the prior turns for `session_id`, or an empty history if the
+    /// session is unknown (treated as a fresh, stateless conversation).
+    pub async fn history(&self, session_id: &str) -> Vec<Message> {
+        self.sessions
+            .lock()
+            .await
+            .get(session_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Appends the user message and the assembled assistant response to an
+    /// existing session, creating it if this is the first turn seen for it.
+    pub async fn append(&self, session_id: &str, user_message: Message, assistant_message: Message) {
+        let mut sessions = self.sessions.lock().await;
+        let history = sessions.entry(session_id.to_string()).or_default();
+        history.push(user_message);
+        history.push(assistant_message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn create_list_delete_round_trip() {
+        let manager = SessionManager::new();
+        let session_id = manager.create().await;
+
+        assert!(manager.list().await.contains(&session_id));
+        assert!(manager.delete(&session_id).await);
+        assert!(!manager.list().await.contains(&session_id));
+    }
+
+    #[tokio::test]
+    async fn delete_unknown_session_returns_false() {
+        let manager = SessionManager::new();
+        assert!(!manager.delete("does-not-exist").await);
+    }
+
+    #[tokio::test]
+    async fn history_is_empty_for_unknown_session() {
+        let manager = SessionManager::new();
+        assert!(manager.history("does-not-exist").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn append_grows_history_by_two_per_turn() {
+        let manager = SessionManager::new();
+        let session_id = manager.create().await;
+
+        manager
+            .append(&session_id, Message::user("hi"), Message::assistant("hello"))
+            .await;
+        assert_eq!(manager.history(&session_id).await.len(), 2);
+
+        manager
+            .append(&session_id, Message::user("again"), Message::assistant("sure"))
+            .await;
+        assert_eq!(manager.history(&session_id).await.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn append_creates_session_if_missing() {
+        let manager = SessionManager::new();
+        manager
+            .append("unregistered", Message::user("hi"), Message::assistant("hello"))
+            .await;
+        assert_eq!(manager.history("unregistered").await.len(), 2);
+    }
+}